@@ -0,0 +1,22 @@
+//! Architecture backends for saving/restoring a coroutine's register state.
+//!
+//! Each backend exposes a `Context` type (the callee-saved registers a
+//! `switch` needs to round-trip) and an `unsafe fn switch(old, new)` that
+//! performs the actual stack swap. The scheduler only ever goes through
+//! these two items; `Runtime::spawn_with_stack` in `main.rs` is the one
+//! exception, with one version per backend that fills in that backend's own
+//! `Context` fields, so adding a new ISA means a module here plus its own
+//! `spawn_with_stack`.
+
+#[cfg(target_arch = "riscv64")]
+mod riscv64;
+#[cfg(target_arch = "riscv64")]
+pub use riscv64::{switch, Context};
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+#[cfg(target_arch = "x86_64")]
+pub use x86_64::{switch, Context};
+
+#[cfg(not(any(target_arch = "riscv64", target_arch = "x86_64")))]
+compile_error!("unsupported architecture: add a backend under src/arch and wire it up here");
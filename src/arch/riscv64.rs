@@ -0,0 +1,76 @@
+//! RISC-V (riscv64) backend.
+//!
+//! Unlike x86-64, `ret` here is just `jalr x0, 0(ra)` — there's no implicit
+//! stack pop, so a freshly spawned thread can't be bootstrapped by pushing
+//! return addresses onto its stack the way `spawn` does for x86. Instead the
+//! context carries the entry point in `ra` (the jump target) and keeps a
+//! separate `nra` ("new" return address) slot for the value that should
+//! become the *real* `ra` register once we land there. On every switch we
+//! mirror the saved `ra` into `nra` too, so for an already-running thread
+//! the two coincide and resuming it behaves like an ordinary `ret`; only a
+//! never-yet-run thread (set up by `spawn`) has them differ, with `nra`
+//! pointing at `guard` so the entry trampoline's own return reaches it.
+
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct Context {
+    pub ra: u64,
+    pub sp: u64,
+    s0: u64,
+    s1: u64,
+    s2: u64,
+    s3: u64,
+    s4: u64,
+    s5: u64,
+    s6: u64,
+    s7: u64,
+    s8: u64,
+    s9: u64,
+    s10: u64,
+    s11: u64,
+    pub nra: u64,
+}
+
+#[naked]
+#[inline(never)]
+pub unsafe fn switch(old: *mut Context, new: *const Context) {
+    asm!("
+        sd      ra, 0x00($0)
+        sd      ra, 0x70($0)
+        sd      sp, 0x08($0)
+        sd      s0, 0x10($0)
+        sd      s1, 0x18($0)
+        sd      s2, 0x20($0)
+        sd      s3, 0x28($0)
+        sd      s4, 0x30($0)
+        sd      s5, 0x38($0)
+        sd      s6, 0x40($0)
+        sd      s7, 0x48($0)
+        sd      s8, 0x50($0)
+        sd      s9, 0x58($0)
+        sd      s10, 0x60($0)
+        sd      s11, 0x68($0)
+
+        ld      t0, 0x00($1)
+        ld      ra, 0x70($1)
+        ld      sp, 0x08($1)
+        ld      s0, 0x10($1)
+        ld      s1, 0x18($1)
+        ld      s2, 0x20($1)
+        ld      s3, 0x28($1)
+        ld      s4, 0x30($1)
+        ld      s5, 0x38($1)
+        ld      s6, 0x40($1)
+        ld      s7, 0x48($1)
+        ld      s8, 0x50($1)
+        ld      s9, 0x58($1)
+        ld      s10, 0x60($1)
+        ld      s11, 0x68($1)
+        jr      t0
+        "
+    :
+    :"r"(old), "r"(new)
+    :
+    : "volatile", "alignstack"
+    );
+}
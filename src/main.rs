@@ -1,11 +1,32 @@
 #![feature(asm)]
 #![feature(naked_functions)]
+use std::any::Any;
+use std::cell::RefCell;
+use std::marker::PhantomData;
 use std::ptr;
+use std::rc::Rc;
+
+mod arch;
+use arch::{switch, Context};
+pub mod sync;
 
 const DEFAULT_STACK_SIZE: usize = 1024 * 1024 * 2;
+// Just the size of the initial pool; `Runtime::spawn` grows it on demand once
+// every slot is taken (see `Runtime::reserve_thread`).
 const MAX_THREADS: usize = 4;
+// The bootstrap layout (`call`/`guard` return slots, 16-byte alignment) needs
+// a little headroom below the top of the stack, and the coroutine itself
+// needs real room to run; anything smaller than this is almost certainly a
+// mistake rather than an intentionally tiny goroutine-style stack.
+const MIN_STACK_SIZE: usize = 1024 * 4;
 static mut RUNTIME: usize = 0;
 
+// The closure is boxed twice so that the pointer we stash in
+// `Thread::closure` (a plain `u64`) stays thin, even though the inner `Box`
+// is a fat pointer to a trait object.
+type Erased = Box<dyn Any>;
+type BoxedClosure = Box<dyn FnOnce() -> Erased>;
+
 pub struct Runtime {
     threads: Vec<Thread>,
     current: usize,
@@ -16,46 +37,101 @@ enum State {
     Available,
     Running,
     Ready,
+    Blocked,
 }
 
 struct Thread {
     id: usize,
     stack: Vec<u8>,
-    ctx: ThreadContext,
+    ctx: Context,
     state: State,
+    closure: u64,
+    result: Rc<RefCell<Option<Erased>>>,
+    // Who to switch back to when this thread yields or finishes, if it was
+    // reached via `Runtime::resume` rather than the round-robin scheduler.
+    resumer: Option<usize>,
+    outbox: Rc<RefCell<Option<Erased>>>,
+}
+
+/// A handle to a spawned thread that lets the caller cooperatively wait for
+/// it to finish and collect the value it returned, or drive it like a
+/// generator via [`JoinHandle::resume`].
+pub struct JoinHandle<T> {
+    id: usize,
+    result: Rc<RefCell<Option<Erased>>>,
+    _marker: PhantomData<T>,
 }
 
-#[cfg(not(target_os = "windows"))]
-#[derive(Debug, Default)]
-#[repr(C)]
-struct ThreadContext {
-    rsp: u64,
-    r15: u64,
-    r14: u64,
-    r13: u64,
-    r12: u64,
-    rbx: u64,
-    rbp: u64,
+impl<T: 'static> JoinHandle<T> {
+    /// Yields the current thread until this handle's own result has been
+    /// populated, then returns the value it produced.
+    ///
+    /// This checks `self.result` rather than the pool slot's `State`: once
+    /// the thread finishes, `reserve_thread` is free to recycle its slot for
+    /// an unrelated later `spawn`, so polling by id would end up waiting on
+    /// whatever coroutine happens to occupy that slot next.
+    pub fn join(self) -> T {
+        while self.result.borrow().is_none() {
+            yield_thread();
+        }
+
+        let result = self
+            .result
+            .borrow_mut()
+            .take()
+            .expect("thread finished without a result");
+        *result
+            .downcast::<T>()
+            .expect("join handle result type mismatch")
+    }
+
+    /// Switches into the target thread, returning the value it passes to
+    /// [`yield_value`], or `None` once it runs to completion (its final
+    /// return value is then available through [`JoinHandle::join`]).
+    pub fn resume(&self) -> Option<T> {
+        unsafe {
+            let rt_ptr = RUNTIME as *mut Runtime;
+            (*rt_ptr).resume(self.id)
+        }
+    }
 }
 
 impl Thread {
+    // No stack yet: allocating `DEFAULT_STACK_SIZE` (or more) bytes per
+    // pooled thread before it's ever scheduled would waste memory on a pool
+    // that mostly sits `Available`. `spawn_with_stack` allocates the stack
+    // lazily, once there's an actual closure to run on it.
     fn new(id: usize) -> Self {
         Thread {
             id,
-            stack: vec![0_u8; DEFAULT_STACK_SIZE],
-            ctx: ThreadContext::default(),
+            stack: Vec::new(),
+            ctx: Context::default(),
             state: State::Available,
+            closure: 0,
+            result: Rc::new(RefCell::new(None)),
+            resumer: None,
+            outbox: Rc::new(RefCell::new(None)),
         }
     }
 }
 
+/// Rounds a pointer down to the given power-of-two alignment.
+fn align_down(ptr: *mut u8, align: usize) -> *mut u8 {
+    debug_assert!(align.is_power_of_two());
+    (ptr as usize & !(align - 1)) as *mut u8
+}
+
 impl Runtime {
     pub fn new() -> Self {
         let base_thread = Thread {
             id: 0,
-            stack: vec![0_u8; DEFAULT_STACK_SIZE],
-            ctx: ThreadContext::default(),
+            stack: Vec::new(),
+            ctx: Context::default(),
             state: State::Running,
+            closure: 0,
+            result: Rc::new(RefCell::new(None)),
+            resumer: None,
+            outbox: Rc::new(RefCell::new(None)),
         };
 
         let mut threads = vec![base_thread];
@@ -83,7 +159,13 @@ impl Runtime {
     fn t_return(&mut self) {
         if self.current != 0 {
             self.threads[self.current].state = State::Available;
-            self.t_yield();
+            // A thread reached via `resume` finishes back into its resumer,
+            // not whatever the round-robin scan would have picked next.
+            if let Some(resumer) = self.threads[self.current].resumer {
+                self.switch_to(resumer);
+            } else {
+                self.t_yield();
+            }
         }
     }
 
@@ -95,42 +177,207 @@ impl Runtime {
                 pos = 0;
             }
             if pos == self.current {
+                // No other thread is ready to run. If at least one is merely
+                // `Blocked` (as opposed to `Available`, i.e. finished), this
+                // is treated as a deadlock, even when the calling thread is
+                // itself still `Running` and could call `unpark` later.
+                if self.threads.iter().any(|t| t.state == State::Blocked) {
+                    panic!("deadlock: all threads are blocked with nothing left to unpark them");
+                }
                 return false;
             }
         }
 
-        if self.threads[self.current].state != State::Available {
+        self.switch_to(pos);
+
+        // preventing compiler optimizing our code away on windows. Reached once this
+        // thread is switched back to, and always true.
+        self.threads.len() > 0
+    }
+
+    /// Unconditionally switches execution to `target`, independent of the
+    /// round-robin scan `t_yield` does. Used both by `t_yield` itself (once
+    /// it has picked a target) and by the generator `resume`/`yield_value`
+    /// pair, which always know exactly who to switch to.
+    fn switch_to(&mut self, target: usize) {
+        if self.threads[self.current].state != State::Available
+            && self.threads[self.current].state != State::Blocked
+        {
             self.threads[self.current].state = State::Ready;
         }
 
-        self.threads[pos].state = State::Running;
+        self.threads[target].state = State::Running;
         let old_pos = self.current;
-        self.current = pos;
+        self.current = target;
 
         unsafe {
-            switch(&mut self.threads[old_pos].ctx, &self.threads[pos].ctx);
+            switch(&mut self.threads[old_pos].ctx, &self.threads[target].ctx);
+        }
+    }
+
+    /// Switches into `id`, running it until it either calls [`yield_value`]
+    /// (returning `Some` of that value) or finishes (returning `None`; its
+    /// final return value is then available via `JoinHandle::join`).
+    pub fn resume<T: 'static>(&mut self, id: usize) -> Option<T> {
+        // `id` may already have run to completion — its native stack frame
+        // unwound through `guard`/`t_return` and is gone, so switching into
+        // it again would be undefined behavior rather than a clean no-op.
+        if self.threads[id].state == State::Available {
+            return None;
         }
 
-        // preventing compiler optimizing our code away on windows. Will never be reached anyway.
-        self.threads.len() > 0
+        self.threads[id].resumer = Some(self.current);
+        self.switch_to(id);
+
+        if self.threads[id].state == State::Available {
+            return None;
+        }
+
+        let value = self.threads[id]
+            .outbox
+            .borrow_mut()
+            .take()
+            .expect("thread resumed but neither yielded nor finished");
+        Some(*value.downcast::<T>().expect("resume value type mismatch"))
     }
 
-    #[cfg(not(target_os = "windows"))]
-    pub fn spawn(&mut self, f: fn()) {
-        let available = self
+    /// Finds an `Available` thread to reuse, or grows the pool by pushing a
+    /// fresh one, and returns its index. Threads start with no stack
+    /// allocated at all (see `Thread::new`); `spawn_with_stack` is what
+    /// actually gives one a stack, sized to whatever was asked for.
+    fn reserve_thread(&mut self) -> usize {
+        let idx = match self
             .threads
-            .iter_mut()
-            .find(|t| t.state == State::Available)
-            .expect("no available thread.");
+            .iter()
+            .position(|t| t.state == State::Available)
+        {
+            Some(pos) => pos,
+            None => {
+                let id = self.threads.len();
+                self.threads.push(Thread::new(id));
+                id
+            }
+        };
+
+        // A recycled slot may still carry `resumer`/`outbox` left over from
+        // whatever generator used to live here; clear them so `t_return`
+        // falls back to plain round-robin scheduling for this new coroutine
+        // instead of switching into a stale `resumer` index.
+        let thread = &mut self.threads[idx];
+        thread.resumer = None;
+        thread.outbox = Rc::new(RefCell::new(None));
+        idx
+    }
+
+    /// Spawns `f` with the default stack size. See [`Runtime::spawn_with_stack`]
+    /// to pick a smaller (or larger) stack per-coroutine.
+    pub fn spawn<F, T>(&mut self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        self.spawn_with_stack(DEFAULT_STACK_SIZE, f)
+    }
+
+    #[cfg(all(target_arch = "x86_64", not(target_os = "windows")))]
+    pub fn spawn_with_stack<F, T>(&mut self, stack_size: usize, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        assert!(
+            stack_size >= MIN_STACK_SIZE,
+            "stack_size must be at least {} bytes",
+            MIN_STACK_SIZE
+        );
+        let idx = self.reserve_thread();
+        let available = &mut self.threads[idx];
+        available.stack = vec![0_u8; stack_size];
+
+        // `call` reaches into the current thread to find the closure, so
+        // nothing of it goes on the new stack: the thin pointer to the
+        // double-boxed closure is kept in `Thread::closure`, and the stack
+        // only holds the `call`/`guard` return slots laid out below.
+        let closure: BoxedClosure = Box::new(move || Box::new(f()) as Erased);
+        available.closure = Box::into_raw(Box::new(closure)) as u64;
+        available.result = Rc::new(RefCell::new(None));
+
+        let size = available.stack.len();
+        let s_ptr = available.stack.as_mut_ptr();
+        unsafe {
+            // `call` is reached via `ret`, which behaves like the tail end of
+            // a `call` instruction: it must sit on a 16-byte boundary so that
+            // once it pops off the stack rsp % 16 == 8, matching what the
+            // ABI would have produced for a real call. Align the top of the
+            // stack down first, then lay out the two return slots below it.
+            let aligned_top = align_down(s_ptr.add(size), 16);
+            let call_addr = aligned_top.sub(32);
+            ptr::write(call_addr.add(8) as *mut u64, guard as u64);
+            ptr::write(call_addr as *mut u64, call as u64);
+            available.ctx.rsp = call_addr as u64;
+        }
+        available.state = State::Ready;
+
+        JoinHandle {
+            id: available.id,
+            result: Rc::clone(&available.result),
+            _marker: PhantomData,
+        }
+    }
+
+    #[cfg(target_arch = "riscv64")]
+    pub fn spawn_with_stack<F, T>(&mut self, stack_size: usize, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        assert!(
+            stack_size >= MIN_STACK_SIZE,
+            "stack_size must be at least {} bytes",
+            MIN_STACK_SIZE
+        );
+        let idx = self.reserve_thread();
+        let available = &mut self.threads[idx];
+        available.stack = vec![0_u8; stack_size];
 
+        let closure: BoxedClosure = Box::new(move || Box::new(f()) as Erased);
+        available.closure = Box::into_raw(Box::new(closure)) as u64;
+        available.result = Rc::new(RefCell::new(None));
+
+        // No return addresses to push here: RISC-V's `ret` reads `ra`
+        // directly, so we just point the context at the top of a clean,
+        // 16-byte-aligned stack and let `switch` (see arch::riscv64) land on
+        // `call` with `ra` already set to `guard`.
         let size = available.stack.len();
         let s_ptr = available.stack.as_mut_ptr();
         unsafe {
-            ptr::write(s_ptr.offset((size - 24) as isize) as *mut u64, guard as u64);
-            ptr::write(s_ptr.offset((size - 32) as isize) as *mut u64, f as u64);
-            available.ctx.rsp = s_ptr.offset((size - 32) as isize) as u64;
+            available.ctx.sp = align_down(s_ptr.add(size), 16) as u64;
         }
+        available.ctx.ra = call as u64;
+        available.ctx.nra = guard as u64;
         available.state = State::Ready;
+
+        JoinHandle {
+            id: available.id,
+            result: Rc::clone(&available.result),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Trampoline that a new thread starts in (on x86-64 its stack "returns"
+/// into it; on RISC-V `switch` jumps to it via `Context::ra`): it recovers the
+/// boxed closure stashed by `spawn`, runs it, and stores the result before
+/// falling through (via `ret`) into `guard`.
+fn call() {
+    unsafe {
+        let rt_ptr = RUNTIME as *mut Runtime;
+        let rt = &mut *rt_ptr;
+        let thread = &mut rt.threads[rt.current];
+        let closure = Box::from_raw(thread.closure as *mut BoxedClosure);
+        thread.closure = 0;
+        let result = closure();
+        *thread.result.borrow_mut() = Some(result);
     }
 }
 
@@ -148,33 +395,53 @@ pub fn yield_thread() {
     };
 }
 
-#[cfg(not(target_os = "windows"))]
-#[naked]
-#[inline(never)]
-unsafe fn switch(old: *mut ThreadContext, new: *const ThreadContext) {
-    asm!("
-        mov     %rsp, 0x00($0)
-        mov     %r15, 0x08($0)
-        mov     %r14, 0x10($0)
-        mov     %r13, 0x18($0)
-        mov     %r12, 0x20($0)
-        mov     %rbx, 0x28($0)
-        mov     %rbp, 0x30($0)
-   
-        mov     0x00($1), %rsp
-        mov     0x08($1), %r15
-        mov     0x10($1), %r14
-        mov     0x18($1), %r13
-        mov     0x20($1), %r12
-        mov     0x28($1), %rbx
-        mov     0x30($1), %rbp
-        ret
-        "
-    :
-    :"r"(old), "r"(new)
-    :
-    : "volatile", "alignstack"
-    );
+/// Blocks the current thread: marks it `State::Blocked` and yields, so the
+/// scheduler won't consider it runnable again until some other thread calls
+/// [`unpark`] with its id.
+pub fn park() {
+    unsafe {
+        let rt_ptr = RUNTIME as *mut Runtime;
+        let rt = &mut *rt_ptr;
+        rt.threads[rt.current].state = State::Blocked;
+        rt.t_yield();
+    }
+}
+
+/// Wakes a thread previously blocked with [`park`], moving it back to
+/// `State::Ready`. A no-op if the thread isn't currently blocked.
+pub fn unpark(id: usize) {
+    unsafe {
+        let rt_ptr = RUNTIME as *mut Runtime;
+        let rt = &mut *rt_ptr;
+        if rt.threads[id].state == State::Blocked {
+            rt.threads[id].state = State::Ready;
+        }
+    }
+}
+
+/// The id of the thread currently running, for code that needs to record
+/// "who's waiting" (e.g. the `sync` primitives).
+pub(crate) fn current_thread_id() -> usize {
+    unsafe {
+        let rt_ptr = RUNTIME as *const Runtime;
+        (*rt_ptr).current
+    }
+}
+
+/// Yields a value back to whoever called [`Runtime::resume`] on this thread,
+/// switching straight back to them rather than round-robin-ing to the next
+/// ready thread. Turns a spawned coroutine into a generator.
+pub fn yield_value<T: 'static>(v: T) {
+    unsafe {
+        let rt_ptr = RUNTIME as *mut Runtime;
+        let rt = &mut *rt_ptr;
+        let current = rt.current;
+        *rt.threads[current].outbox.borrow_mut() = Some(Box::new(v));
+        let resumer = rt.threads[current]
+            .resumer
+            .expect("yield_value called on a thread that wasn't resumed");
+        rt.switch_to(resumer);
+    }
 }
 
 fn main() {
@@ -201,113 +468,127 @@ fn main() {
     runtime.run();
 }
 
-// ===== WINDOWS SUPPORT =====
-#[cfg(target_os = "windows")]
-#[derive(Debug, Default)]
-#[repr(C)]
-struct ThreadContext {
-    rsp: u64,
-    r15: u64,
-    r14: u64,
-    r13: u64,
-    r12: u64,
-    rbx: u64,
-    rbp: u64,
-    xmm6: u64,
-    xmm7: u64,
-    xmm8: u64,
-    xmm9: u64,
-    xmm10: u64,
-    xmm11: u64,
-    xmm12: u64,
-    xmm13: u64,
-    xmm14: u64,
-    xmm15: u64,
-    stack_start: u64,
-    stack_end: u64,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Regression test for stack misalignment: in release mode the optimizer
+    // is free to lower this closure's f32x4 work to `movaps`, which faults
+    // with a GP fault if `rsp` isn't 16-byte aligned at the `call`/`ret`
+    // boundary `spawn` sets up.
+    #[test]
+    fn spawned_closure_can_use_aligned_simd() {
+        #[repr(align(16))]
+        struct Vec4([f32; 4]);
+
+        let mut runtime = Runtime::new();
+        runtime.init();
+        let handle = runtime.spawn(|| {
+            let a = Vec4([1.0, 2.0, 3.0, 4.0]);
+            let b = Vec4([5.0, 6.0, 7.0, 8.0]);
+            let mut sum = Vec4([0.0; 4]);
+            for i in 0..4 {
+                sum.0[i] = a.0[i] + b.0[i];
+            }
+            yield_thread();
+            sum.0.iter().sum::<f32>()
+        });
+        while runtime.t_yield() {}
+        assert_eq!(handle.join(), 36.0);
+    }
+
+    #[test]
+    fn generator_yields_values_then_finishes() {
+        let mut runtime = Runtime::new();
+        runtime.init();
+        let handle = runtime.spawn(|| {
+            for i in 1..=3 {
+                yield_value(i);
+            }
+            "done"
+        });
+
+        assert_eq!(runtime.resume::<i32>(handle.id), Some(1));
+        assert_eq!(runtime.resume::<i32>(handle.id), Some(2));
+        assert_eq!(runtime.resume::<i32>(handle.id), Some(3));
+        assert_eq!(runtime.resume::<i32>(handle.id), None);
+        assert_eq!(handle.join(), "done");
+    }
+
+    #[test]
+    fn spawn_grows_the_pool_past_the_initial_capacity() {
+        let mut runtime = Runtime::new();
+        runtime.init();
+
+        // MAX_THREADS is only the size of the initial pool; spawning more
+        // than that should grow it via `reserve_thread` rather than panic.
+        let handles: Vec<_> = (0..MAX_THREADS * 3)
+            .map(|i| runtime.spawn(move || i * 2))
+            .collect();
+
+        while runtime.t_yield() {}
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join(), i * 2);
+        }
+    }
+
+    #[test]
+    fn spawn_with_stack_honors_a_custom_stack_size() {
+        let mut runtime = Runtime::new();
+        runtime.init();
+        let handle = runtime.spawn_with_stack(MIN_STACK_SIZE, || {
+            yield_thread();
+            42
+        });
+        while runtime.t_yield() {}
+        assert_eq!(handle.join(), 42);
+    }
 }
 
+// ===== WINDOWS SUPPORT =====
 impl Runtime {
-    #[cfg(target_os = "windows")]
-    pub fn spawn(&mut self, f: fn()) {
-        let available = self
-            .threads
-            .iter_mut()
-            .find(|t| t.state == State::Available)
-            .expect("no available thread.");
+    #[cfg(all(target_arch = "x86_64", target_os = "windows"))]
+    pub fn spawn_with_stack<F, T>(&mut self, stack_size: usize, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        assert!(
+            stack_size >= MIN_STACK_SIZE,
+            "stack_size must be at least {} bytes",
+            MIN_STACK_SIZE
+        );
+        let idx = self.reserve_thread();
+        let available = &mut self.threads[idx];
+        available.stack = vec![0_u8; stack_size];
+
+        let closure: BoxedClosure = Box::new(move || Box::new(f()) as Erased);
+        available.closure = Box::into_raw(Box::new(closure)) as u64;
+        available.result = Rc::new(RefCell::new(None));
 
         let size = available.stack.len();
         let s_ptr = available.stack.as_mut_ptr();
 
         // see: https://docs.microsoft.com/en-us/cpp/build/stack-usage?view=vs-2019#stack-allocation
         unsafe {
-            ptr::write(s_ptr.offset((size - 40) as isize) as *mut u64, guard as u64);
-            ptr::write(s_ptr.offset((size - 48) as isize) as *mut u64, f as u64);
-            available.ctx.rsp = s_ptr.offset((size - 48) as isize) as u64;
+            // Same 16-byte alignment requirement as the non-Windows path:
+            // `call` is reached via `ret`, so its slot must sit on a 16-byte
+            // boundary.
+            let aligned_top = align_down(s_ptr.add(size), 16);
+            let call_addr = aligned_top.sub(48);
+            ptr::write(call_addr.add(8) as *mut u64, guard as u64);
+            ptr::write(call_addr as *mut u64, call as u64);
+            available.ctx.rsp = call_addr as u64;
             available.ctx.stack_start = s_ptr.offset(size as isize) as u64;
         }
         available.ctx.stack_end = s_ptr as *const u64 as u64;
 
         available.state = State::Ready;
-    }
-}
 
-// reference: https://probablydance.com/2013/02/20/handmade-coroutines-for-windows/
-// Contents of TIB on Windows: https://en.wikipedia.org/wiki/Win32_Thread_Information_Block
-#[cfg(target_os = "windows")]
-#[naked]
-#[inline(never)]
-unsafe fn switch(old: *mut ThreadContext, new: *const ThreadContext) {
-    asm!("
-        mov     %rsp, 0x00($0)
-        mov     %r15, 0x08($0)
-        mov     %r14, 0x10($0)
-        mov     %r13, 0x18($0)
-        mov     %r12, 0x20($0)
-        mov     %rbx, 0x28($0)
-        mov     %rbp, 0x30($0)
-        mov     %xmm6, 0x38($0)
-        mov     %xmm7, 0x40($0)
-        mov     %xmm8, 0x48($0)
-        mov     %xmm9, 0x50($0)
-        mov     %xmm10, 0x58($0)
-        mov     %xmm11, 0x60($0)
-        mov     %xmm12, 0x68($0)
-        mov     %xmm13, 0x70($0)
-        mov     %xmm14, 0x78($0)
-        mov     %xmm15, 0x80($0)
-        mov     %gs:0x08, %rax    
-        mov     %rax, 0x88($0)  
-        mov     %gs:0x10, %rax    
-        mov     %rax, 0x90($0)  
-
-        mov     0x00($1), %rsp
-        mov     0x08($1), %r15
-        mov     0x10($1), %r14
-        mov     0x18($1), %r13
-        mov     0x20($1), %r12
-        mov     0x28($1), %rbx
-        mov     0x30($1), %rbp
-        mov     0x38($1), %xmm6
-        mov     0x40($1), %xmm7
-        mov     0x48($1), %xmm8
-        mov     0x50($1), %xmm9
-        mov     0x58($1), %xmm10
-        mov     0x60($1), %xmm11
-        mov     0x68($1), %xmm12
-        mov     0x70($1), %xmm13
-        mov     0x78($1), %xmm14
-        mov     0x80($1), %xmm15
-        mov     0x88($1), %rax
-        mov     %rax, %gs:0x08  
-        mov     0x90($1), %rax 
-        mov     %rax, %gs:0x10  
-
-        ret
-        "
-    :
-    :"r"(old), "r"(new)
-    :
-    : "volatile", "alignstack"
-    );
+        JoinHandle {
+            id: available.id,
+            result: Rc::clone(&available.result),
+            _marker: PhantomData,
+        }
+    }
 }
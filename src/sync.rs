@@ -0,0 +1,269 @@
+//! User-space blocking primitives built on top of [`crate::park`] /
+//! [`crate::unpark`]. The runtime is purely cooperative and single-threaded,
+//! so these don't need any actual synchronization (no atomics, no locks
+//! around the bookkeeping) — only one coroutine is ever running at a time,
+//! and one that has to wait here is parked until woken by `unpark`.
+
+use crate::{current_thread_id, park, unpark};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+struct ChannelInner<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    senders: usize,
+    closed: bool,
+    waiting_senders: VecDeque<usize>,
+    waiting_receiver: Option<usize>,
+}
+
+/// The sending half of a bounded MPSC channel.
+pub struct Sender<T> {
+    inner: Rc<RefCell<ChannelInner<T>>>,
+}
+
+/// The receiving half of a bounded MPSC channel.
+pub struct Receiver<T> {
+    inner: Rc<RefCell<ChannelInner<T>>>,
+}
+
+/// Creates a bounded channel that blocks the sender once `capacity` values
+/// are queued, and blocks the receiver while it's empty.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "channel capacity must be positive");
+    let inner = Rc::new(RefCell::new(ChannelInner {
+        queue: VecDeque::with_capacity(capacity),
+        capacity,
+        senders: 1,
+        closed: false,
+        waiting_senders: VecDeque::new(),
+        waiting_receiver: None,
+    }));
+    (
+        Sender {
+            inner: Rc::clone(&inner),
+        },
+        Receiver { inner },
+    )
+}
+
+impl<T> Sender<T> {
+    /// Cooperatively blocks (parking the current thread) until there's room
+    /// in the channel, then pushes `value`.
+    pub fn send(&self, value: T) {
+        let mut value = Some(value);
+        loop {
+            {
+                let mut inner = self.inner.borrow_mut();
+                if inner.queue.len() < inner.capacity {
+                    inner.queue.push_back(value.take().unwrap());
+                    if let Some(id) = inner.waiting_receiver.take() {
+                        unpark(id);
+                    }
+                    return;
+                }
+                inner.waiting_senders.push_back(current_thread_id());
+            }
+            park();
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.inner.borrow_mut().senders += 1;
+        Sender {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.senders -= 1;
+        if inner.senders == 0 {
+            inner.closed = true;
+            if let Some(id) = inner.waiting_receiver.take() {
+                unpark(id);
+            }
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Cooperatively blocks until a value is available, returning `None`
+    /// once the channel is empty and every `Sender` has been dropped.
+    pub fn recv(&self) -> Option<T> {
+        loop {
+            {
+                let mut inner = self.inner.borrow_mut();
+                if let Some(value) = inner.queue.pop_front() {
+                    if let Some(id) = inner.waiting_senders.pop_front() {
+                        unpark(id);
+                    }
+                    return Some(value);
+                }
+                if inner.closed {
+                    return None;
+                }
+                inner.waiting_receiver = Some(current_thread_id());
+            }
+            park();
+        }
+    }
+}
+
+/// A mutual-exclusion lock built on `park`/`unpark` rather than a spin loop.
+pub struct Mutex<T> {
+    locked: RefCell<bool>,
+    waiting: RefCell<VecDeque<usize>>,
+    data: RefCell<T>,
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Mutex {
+            locked: RefCell::new(false),
+            waiting: RefCell::new(VecDeque::new()),
+            data: RefCell::new(value),
+        }
+    }
+
+    /// Parks the current thread until the lock is free, then acquires it.
+    pub fn lock(&self) -> MutexGuard<T> {
+        loop {
+            if !*self.locked.borrow() {
+                *self.locked.borrow_mut() = true;
+                return MutexGuard { mutex: self };
+            }
+            self.waiting.borrow_mut().push_back(current_thread_id());
+            park();
+        }
+    }
+
+    fn unlock(&self) {
+        *self.locked.borrow_mut() = false;
+        if let Some(id) = self.waiting.borrow_mut().pop_front() {
+            unpark(id);
+        }
+    }
+}
+
+/// RAII guard returned by [`Mutex::lock`]; releases the lock on drop.
+pub struct MutexGuard<'m, T> {
+    mutex: &'m Mutex<T>,
+}
+
+impl<'m, T> Deref for MutexGuard<'m, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.as_ptr() }
+    }
+}
+
+impl<'m, T> DerefMut for MutexGuard<'m, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.as_ptr() }
+    }
+}
+
+impl<'m, T> Drop for MutexGuard<'m, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+/// A condition variable that parks waiters and wakes them via `unpark`,
+/// meant to be used alongside a [`Mutex`] exactly like `std::sync::Condvar`.
+pub struct Condvar {
+    waiting: RefCell<VecDeque<usize>>,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Condvar {
+            waiting: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// Releases `guard`'s lock, parks until notified, then reacquires it.
+    pub fn wait<'m, T>(&self, guard: MutexGuard<'m, T>) -> MutexGuard<'m, T> {
+        let mutex = guard.mutex;
+        self.waiting.borrow_mut().push_back(current_thread_id());
+        drop(guard);
+        park();
+        mutex.lock()
+    }
+
+    pub fn notify_one(&self) {
+        if let Some(id) = self.waiting.borrow_mut().pop_front() {
+            unpark(id);
+        }
+    }
+
+    pub fn notify_all(&self) {
+        while let Some(id) = self.waiting.borrow_mut().pop_front() {
+            unpark(id);
+        }
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runtime;
+
+    #[test]
+    fn channel_blocks_until_capacity_and_delivers_in_order() {
+        let mut runtime = Runtime::new();
+        runtime.init();
+        let (tx, rx) = channel::<i32>(1);
+
+        runtime.spawn(move || {
+            for i in 0..3 {
+                tx.send(i);
+            }
+        });
+        let handle = runtime.spawn(move || {
+            let mut received = Vec::new();
+            while let Some(v) = rx.recv() {
+                received.push(v);
+            }
+            received
+        });
+
+        while runtime.t_yield() {}
+        assert_eq!(handle.join(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn mutex_serializes_access_across_threads() {
+        let mut runtime = Runtime::new();
+        runtime.init();
+        let mutex = Rc::new(Mutex::new(0));
+
+        for _ in 0..2 {
+            let mutex = Rc::clone(&mutex);
+            runtime.spawn(move || {
+                for _ in 0..5 {
+                    let mut guard = mutex.lock();
+                    *guard += 1;
+                    crate::yield_thread();
+                }
+            });
+        }
+
+        while runtime.t_yield() {}
+        assert_eq!(*mutex.lock(), 10);
+    }
+}